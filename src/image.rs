@@ -1,58 +1,58 @@
 //! Image data structure and manipulation utilities.
 //!
 //! This module provides a simple 2D image structure for storing and manipulating
-//! grayscale image data with support for scaling and pixel access.
+//! pixel data with support for scaling and pixel access. Images may carry one
+//! channel (grayscale), three channels (RGB), or four channels (RGBA); channel
+//! data is stored interleaved, matching the layout TIFF decoders hand back.
 
+use rayon::prelude::*;
 use std::ops::{Index, IndexMut};
 
-/// A 2D grayscale image with row-major pixel storage.
+/// A 2D image with row-major, interleaved pixel storage.
 ///
-/// The image stores pixel data in a flat vector where pixels are arranged in
-/// row-major order (i.e., pixels[i * ncols + j] corresponds to pixel (i, j)).
+/// The image stores pixel data in a flat vector where samples are arranged in
+/// row-major order with `channels` interleaved samples per pixel (i.e. pixel
+/// `(i, j)` occupies `pixels[(i * ncols + j) * channels .. (i * ncols + j + 1) * channels]`).
 #[derive(Clone, Debug)]
 pub struct Image {
     /// Number of rows in the image
     nrows: usize,
     /// Number of columns in the image
     ncols: usize,
-    /// Pixel data in row-major order
+    /// Number of interleaved samples per pixel (1 = gray, 3 = RGB, 4 = RGBA)
+    channels: usize,
+    /// Pixel data in row-major, interleaved order
     pixels: Vec<u8>,
 }
 
 impl Image {
-    /// Creates a new image with all pixels initialized to zero.
+    /// Creates an image from existing interleaved pixel data.
     ///
     /// # Arguments
     ///
     /// * `nrows` - Number of rows in the image
     /// * `ncols` - Number of columns in the image
-    pub fn new(nrows: usize, ncols: usize) -> Self {
-        let pixels = vec![0; nrows * ncols];
-        Image {
-            nrows,
-            ncols,
-            pixels,
-        }
-    }
-    /// Creates an image from existing pixel data.
-    ///
-    /// # Arguments
-    ///
-    /// * `nrows` - Number of rows in the image
-    /// * `ncols` - Number of columns in the image
-    /// * `pixels` - Pixel data in row-major order
+    /// * `channels` - Number of interleaved samples per pixel
+    /// * `pixels` - Pixel data in row-major, interleaved order
     ///
     /// # Panics
     ///
-    /// Panics if `pixels.len()` does not equal `nrows * ncols`.
-    pub fn from_vec(nrows: usize, ncols: usize, pixels: Vec<u8>) -> Self {
-        assert_eq!(pixels.len(), nrows * ncols);
+    /// Panics if `pixels.len()` does not equal `nrows * ncols * channels`.
+    pub fn from_vec_with_channels(
+        nrows: usize,
+        ncols: usize,
+        channels: usize,
+        pixels: Vec<u8>,
+    ) -> Self {
+        assert_eq!(pixels.len(), nrows * ncols * channels);
         Image {
             nrows,
             ncols,
+            channels,
             pixels,
         }
     }
+
     /// Returns the number of rows in the image.
     pub fn rows(&self) -> usize {
         self.nrows
@@ -63,63 +63,257 @@ impl Image {
         self.ncols
     }
 
-    /// Returns the pixel data as a slice.
+    /// Returns the number of interleaved samples per pixel.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Returns the pixel data as a slice of interleaved samples.
     pub fn as_slice(&self) -> &[u8] {
         &self.pixels
     }
 
-    /// Scales the image by the given factor using bilinear interpolation.
+    /// Returns a single sample (channel) of the pixel at (row, col).
+    pub fn sample(&self, row: usize, col: usize, channel: usize) -> u8 {
+        self.pixels[(row * self.ncols + col) * self.channels + channel]
+    }
+
+    /// Scales the image by the given factor using the selected resampling filter.
+    ///
+    /// Resizing is separable: the image is resampled along columns, then
+    /// along rows, each a 1-D convolution over a per-axis table of source
+    /// indices and weights (built once from the axis lengths and `mode`, not
+    /// from pixel data). Both passes run their rows in parallel via `rayon`.
     ///
     /// # Arguments
     ///
     /// * `factor` - Scaling factor (>1.0 enlarges, <1.0 shrinks)
+    /// * `mode` - Resampling filter to use
     ///
     /// # Returns
     ///
     /// A new scaled image with dimensions `(nrows * factor, ncols * factor)`.
-    pub fn scale(&self, factor: f32) -> Image {
+    pub fn scale_with_mode(&self, factor: f32, mode: ResampleMode) -> Image {
+        let (w, h) = self.output_dims(factor);
+        let channels = self.channels;
+
+        if w == 0 || h == 0 {
+            return Image::from_vec_with_channels(h, w, channels, Vec::new());
+        }
+
+        let col_taps = resample_taps(self.ncols, w, factor, mode);
+        let row_taps = resample_taps(self.nrows, h, factor, mode);
+
+        // Pass 1: resample along columns, keeping the original row count.
+        let mut intermediate = vec![0.0f32; self.nrows * w * channels];
+        intermediate
+            .par_chunks_mut(w * channels)
+            .enumerate()
+            .for_each(|(i, out_row)| {
+                for (j, taps) in col_taps.iter().enumerate() {
+                    for c in 0..channels {
+                        let acc: f32 = taps
+                            .iter()
+                            .map(|tap| self.sample(i, tap.index, c) as f32 * tap.weight)
+                            .sum();
+                        out_row[j * channels + c] = acc;
+                    }
+                }
+            });
+
+        // Pass 2: resample along rows, reading from the column-resampled buffer.
+        let mut pixels = vec![0u8; h * w * channels];
+        pixels
+            .par_chunks_mut(w * channels)
+            .enumerate()
+            .for_each(|(i, out_row)| {
+                let taps = &row_taps[i];
+                for j in 0..w {
+                    for c in 0..channels {
+                        let acc: f32 = taps
+                            .iter()
+                            .map(|tap| intermediate[(tap.index * w + j) * channels + c] * tap.weight)
+                            .sum();
+                        out_row[j * channels + c] = acc.clamp(0.0, 255.0) as u8;
+                    }
+                }
+            });
+
+        Image::from_vec_with_channels(h, w, channels, pixels)
+    }
+
+    fn output_dims(&self, factor: f32) -> (usize, usize) {
         let w: usize = (self.ncols as f32 * factor) as usize;
         let h: usize = (self.nrows as f32 * factor) as usize;
-        let mut new_pixels = vec![0; w * h];
-        // bilinear interpolation
-        for i in 0..h {
-            for j in 0..w {
-                let x = (i as f32) / factor;
-                let y = (j as f32) / factor;
-                let x0 = x.floor() as usize;
-                let x1 = x0.min(self.nrows - 1);
-                let y0 = y.floor() as usize;
-                let y1 = y0.min(self.ncols - 1);
-                let dx = x - (x0 as f32);
-                let dy = y - (y0 as f32);
-                let p00 = self[(x0, y0)] as f32;
-                let p01 = self[(x0, y1)] as f32;
-                let p10 = self[(x1, y0)] as f32;
-                let p11 = self[(x1, y1)] as f32;
-                let p0 = p00 * (1.0 - dy) + p01 * dy;
-                let p1 = p10 * (1.0 - dy) + p11 * dy;
-                let p = p0 * (1.0 - dx) + p1 * dx;
-                new_pixels[i * w + j] = p.round() as u8;
+        (w, h)
+    }
+}
+
+/// One source index and its contribution weight for a resampled output
+/// coordinate along a single axis.
+struct Tap {
+    index: usize,
+    weight: f32,
+}
+
+fn cubic_weight(s: f32) -> f32 {
+    const A: f32 = -0.5;
+    let s = s.abs();
+    if s <= 1.0 {
+        (A + 2.0) * s.powi(3) - (A + 3.0) * s.powi(2) + 1.0
+    } else if s < 2.0 {
+        A * s.powi(3) - 5.0 * A * s.powi(2) + 8.0 * A * s - 4.0 * A
+    } else {
+        0.0
+    }
+}
+
+/// Precomputes, for every output coordinate along one axis, the source
+/// indices and weights needed to resample it. The table depends only on the
+/// axis lengths, `factor`, and `mode` — not on pixel data — so it is built
+/// once per axis for a given scale and reused for every row (or column) of
+/// that axis's pass; the two passes each use their own axis's table.
+fn resample_taps(src_len: usize, dst_len: usize, factor: f32, mode: ResampleMode) -> Vec<Vec<Tap>> {
+    let clamp_idx = |i: isize| i.clamp(0, src_len as isize - 1) as usize;
+
+    (0..dst_len)
+        .map(|dst| {
+            let x = (dst as f32) / factor;
+            match mode {
+                ResampleMode::Nearest => {
+                    let idx = clamp_idx(x.round() as isize);
+                    vec![Tap { index: idx, weight: 1.0 }]
+                }
+                ResampleMode::Bilinear => {
+                    let x0 = x.floor() as isize;
+                    let t = x - (x0 as f32);
+                    vec![
+                        Tap { index: clamp_idx(x0), weight: 1.0 - t },
+                        Tap { index: clamp_idx(x0 + 1), weight: t },
+                    ]
+                }
+                ResampleMode::Bicubic => {
+                    let x0 = x.floor() as isize;
+                    let t = x - (x0 as f32);
+                    [-1isize, 0, 1, 2]
+                        .iter()
+                        .map(|&offset| {
+                            let dist = match offset {
+                                -1 => 1.0 + t,
+                                0 => t,
+                                1 => 1.0 - t,
+                                _ => 2.0 - t,
+                            };
+                            Tap {
+                                index: clamp_idx(x0 + offset),
+                                weight: cubic_weight(dist),
+                            }
+                        })
+                        .collect()
+                }
+            }
+        })
+        .collect()
+}
+
+/// Resampling filter used when scaling an [`Image`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResampleMode {
+    /// Rounds to the nearest source pixel; crisp, good for pixel-level inspection.
+    Nearest,
+    /// Weighted average of the four nearest source pixels.
+    #[default]
+    Bilinear,
+    /// Catmull-Rom cubic convolution over the 4x4 neighborhood; smoother downscaling.
+    Bicubic,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cubic_weight_known_points() {
+        // At the sampled pixel itself, the Catmull-Rom kernel is 1.
+        assert!((cubic_weight(0.0) - 1.0).abs() < 1e-6);
+        // At the next two integer distances, it's exactly 0.
+        assert!((cubic_weight(1.0)).abs() < 1e-6);
+        assert_eq!(cubic_weight(2.0), 0.0);
+    }
+
+    #[test]
+    fn resample_taps_bicubic_weights_sum_to_one() {
+        // A 4x4 neighborhood's weights must sum to 1 to conserve brightness,
+        // whatever the sub-pixel offset `t`.
+        for dst_len in [5, 6] {
+            let taps = resample_taps(4, dst_len, 1.25, ResampleMode::Bicubic);
+            for taps_for_dst in taps {
+                let sum: f32 = taps_for_dst.iter().map(|t| t.weight).sum();
+                assert!((sum - 1.0).abs() < 1e-5, "weights {:?} sum to {sum}",
+                    taps_for_dst.iter().map(|t| t.weight).collect::<Vec<_>>());
             }
         }
-        Image::from_vec(h, w, new_pixels)
+    }
+
+    #[test]
+    fn resample_taps_nearest_picks_known_indices() {
+        let taps = resample_taps(2, 4, 2.0, ResampleMode::Nearest);
+        let indices: Vec<usize> = taps.iter().map(|t| t[0].index).collect();
+        assert_eq!(indices, vec![0, 1, 1, 1]);
+    }
+
+    #[test]
+    fn resample_taps_bilinear_identity_factor_has_zero_weight_on_neighbor() {
+        // At factor 1.0, every destination coordinate lands exactly on a
+        // source pixel, so the second tap should contribute nothing.
+        let taps = resample_taps(3, 3, 1.0, ResampleMode::Bilinear);
+        for taps_for_dst in taps {
+            assert_eq!(taps_for_dst[1].weight, 0.0);
+        }
+    }
+
+    #[test]
+    fn scale_with_mode_identity_factor_preserves_pixels() {
+        let img = Image::from_vec_with_channels(2, 2, 1, vec![10, 20, 30, 40]);
+        let scaled = img.scale_with_mode(1.0, ResampleMode::Bilinear);
+        assert_eq!((scaled.rows(), scaled.cols()), (2, 2));
+        assert_eq!(scaled.as_slice(), &[10, 20, 30, 40]);
+    }
+
+    #[test]
+    fn scale_with_mode_nearest_upsample_picks_known_source_pixels() {
+        let img = Image::from_vec_with_channels(1, 2, 1, vec![0, 255]);
+        let scaled = img.scale_with_mode(2.0, ResampleMode::Nearest);
+        assert_eq!((scaled.rows(), scaled.cols()), (2, 4));
+        // Row taps replicate the single source row; column taps match
+        // `resample_taps_nearest_picks_known_indices` above: [0, 1, 1, 1].
+        assert_eq!(scaled.as_slice(), &[0, 255, 255, 255, 0, 255, 255, 255]);
+    }
+
+    #[test]
+    fn scale_with_mode_zero_sized_output_does_not_panic() {
+        let img = Image::from_vec_with_channels(10, 10, 3, vec![0; 300]);
+        let scaled = img.scale_with_mode(0.01, ResampleMode::Bilinear);
+        assert_eq!((scaled.rows(), scaled.cols()), (0, 0));
     }
 }
 
 impl Index<(usize, usize)> for Image {
     type Output = u8;
 
-    /// Indexes into the image at position (row, col).
+    /// Indexes into the first channel of the image at position (row, col).
+    ///
+    /// For multi-channel images, use [`Image::sample`] instead.
     fn index(&self, (i, j): (usize, usize)) -> &Self::Output {
-        let flat_idx = i * self.ncols + j;
+        let flat_idx = (i * self.ncols + j) * self.channels;
         &self.pixels[flat_idx]
     }
 }
 
 impl IndexMut<(usize, usize)> for Image {
-    /// Mutably indexes into the image at position (row, col).
+    /// Mutably indexes into the first channel of the image at position (row, col).
     fn index_mut(&mut self, (i, j): (usize, usize)) -> &mut Self::Output {
-        let flat_idx = i * self.ncols + j;
+        let flat_idx = (i * self.ncols + j) * self.channels;
         &mut self.pixels[flat_idx]
     }
 }
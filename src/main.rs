@@ -1,5 +1,7 @@
 mod image;
+mod rawimage;
 mod tifread;
+mod tifwrite;
 mod window;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -10,8 +12,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         std::process::exit(1);
     }
     
-    let images = tifread::read_tiff(&args[1])?;
-    window::run(images)?;
+    let pages = tifread::read_tiff(&args[1])?;
+    window::run(pages)?;
     
     Ok(())
 }
@@ -0,0 +1,204 @@
+//! Raw (undecimated) pixel storage and window/level rendering.
+//!
+//! [`tifread`](crate::tifread) used to bake a global min/max normalization
+//! into 8-bit at load time, discarding the original dynamic range of 16-bit
+//! and floating-point scientific TIFFs. [`RawPage`] instead keeps each page's
+//! samples in their native type and defers the 8-bit mapping to display time,
+//! via an adjustable window center/width (and optional invert), so
+//! re-windowing never needs a re-decode.
+
+use num_traits::cast::ToPrimitive;
+
+use crate::image::Image;
+
+/// A page's decoded samples in their native numeric type, interleaved by
+/// channel exactly like [`tiff::decoder::DecodingResult`].
+#[derive(Clone, Debug)]
+pub enum RawSamples {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+    U64(Vec<u64>),
+    F32(Vec<f32>),
+    F64(Vec<f64>),
+    I8(Vec<i8>),
+    I16(Vec<i16>),
+    I32(Vec<i32>),
+    I64(Vec<i64>),
+}
+
+impl RawSamples {
+    fn len(&self) -> usize {
+        match self {
+            RawSamples::U8(b) => b.len(),
+            RawSamples::U16(b) => b.len(),
+            RawSamples::U32(b) => b.len(),
+            RawSamples::U64(b) => b.len(),
+            RawSamples::F32(b) => b.len(),
+            RawSamples::F64(b) => b.len(),
+            RawSamples::I8(b) => b.len(),
+            RawSamples::I16(b) => b.len(),
+            RawSamples::I32(b) => b.len(),
+            RawSamples::I64(b) => b.len(),
+        }
+    }
+
+    fn sample_f64(&self, index: usize) -> f64 {
+        match self {
+            RawSamples::U8(b) => b[index].to_f64().unwrap_or(0.0),
+            RawSamples::U16(b) => b[index].to_f64().unwrap_or(0.0),
+            RawSamples::U32(b) => b[index].to_f64().unwrap_or(0.0),
+            RawSamples::U64(b) => b[index].to_f64().unwrap_or(0.0),
+            RawSamples::F32(b) => b[index].to_f64().unwrap_or(0.0),
+            RawSamples::F64(b) => b[index],
+            RawSamples::I8(b) => b[index].to_f64().unwrap_or(0.0),
+            RawSamples::I16(b) => b[index].to_f64().unwrap_or(0.0),
+            RawSamples::I32(b) => b[index].to_f64().unwrap_or(0.0),
+            RawSamples::I64(b) => b[index].to_f64().unwrap_or(0.0),
+        }
+    }
+
+    fn min_max(&self) -> (f64, f64) {
+        let n = self.len();
+        if n == 0 {
+            return (0.0, 1.0);
+        }
+        let mut minv = self.sample_f64(0);
+        let mut maxv = minv;
+        for i in 1..n {
+            let v = self.sample_f64(i);
+            if v < minv {
+                minv = v;
+            }
+            if v > maxv {
+                maxv = v;
+            }
+        }
+        if maxv == minv {
+            maxv = minv + 1.0;
+        }
+        (minv, maxv)
+    }
+}
+
+/// One TIFF page's samples in their native bit depth, plus the channel
+/// layout needed to interpret them and the min/max observed in the page
+/// (used to seed the default window/level and auto-contrast).
+#[derive(Clone, Debug)]
+pub struct RawPage {
+    nrows: usize,
+    ncols: usize,
+    channels: usize,
+    samples: RawSamples,
+    min: f64,
+    max: f64,
+}
+
+impl RawPage {
+    /// Wraps decoded samples, computing their min/max up front.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `samples.len()` does not equal `nrows * ncols * channels`.
+    pub fn new(nrows: usize, ncols: usize, channels: usize, samples: RawSamples) -> Self {
+        assert_eq!(samples.len(), nrows * ncols * channels);
+        let (min, max) = samples.min_max();
+        RawPage {
+            nrows,
+            ncols,
+            channels,
+            samples,
+            min,
+            max,
+        }
+    }
+
+    /// Returns the number of rows in the page.
+    pub fn rows(&self) -> usize {
+        self.nrows
+    }
+
+    /// Returns the number of columns in the page.
+    pub fn cols(&self) -> usize {
+        self.ncols
+    }
+
+    /// Returns the minimum sample value observed in the page.
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    /// Returns the maximum sample value observed in the page, or `min + 1`
+    /// when every sample is equal (or the page is empty).
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// Returns the `(center, width)` window that maps the page's full
+    /// observed range onto [0, 255] (i.e. auto-contrast).
+    pub fn auto_window(&self) -> (f64, f64) {
+        let width = (self.max - self.min).max(1e-6);
+        let center = (self.max + self.min) / 2.0;
+        (center, width)
+    }
+
+    /// Returns the `(center, width)` window to use when a page is first
+    /// displayed or navigated to.
+    ///
+    /// Multi-channel 8-bit pages (ordinary RGB/RGBA photos, plus CMYK/YCbCr/
+    /// palette pages, which [`crate::tifread`] already converts to 8-bit RGB)
+    /// default to the identity window (center 127.5, width 255) so they
+    /// display as encoded: real photos essentially never hit exactly 0 and
+    /// 255 in every channel at once, so seeding the default from
+    /// [`RawPage::auto_window`]'s per-page min/max would silently
+    /// contrast-stretch and recolor them on load. Single-channel pages
+    /// (grayscale, and scientific 16-bit/float data in particular) keep
+    /// `auto_window`'s data-driven default, which is what makes those
+    /// usable at all; `auto_window` remains available as the explicit
+    /// "Auto-contrast" opt-in for every page.
+    pub fn default_window(&self) -> (f64, f64) {
+        if self.channels > 1 && matches!(self.samples, RawSamples::U8(_)) {
+            (127.5, 255.0)
+        } else {
+            self.auto_window()
+        }
+    }
+
+    /// Renders the page to an 8-bit [`Image`] using the given window
+    /// center/width, optionally inverted.
+    ///
+    /// `display = clamp((value - (center - width / 2)) / width * 255, 0, 255)`,
+    /// applied independently to every channel.
+    pub fn to_image(&self, center: f64, width: f64, invert: bool) -> Image {
+        let width = width.max(1e-6);
+        let lo = center - width / 2.0;
+        let mut pixels = vec![0u8; self.samples.len()];
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            let value = self.samples.sample_f64(i);
+            let normalized = ((value - lo) / width * 255.0).clamp(0.0, 255.0);
+            let normalized = if invert { 255.0 - normalized } else { normalized };
+            *pixel = normalized.round() as u8;
+        }
+        Image::from_vec_with_channels(self.nrows, self.ncols, self.channels, pixels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_image_maps_known_values_through_window() {
+        // Window [0, 200]: 0 -> black, 100 -> mid-gray, 200 -> white, clamped beyond.
+        let page = RawPage::new(1, 4, 1, RawSamples::U16(vec![0, 100, 200, 255]));
+        let img = page.to_image(100.0, 200.0, false);
+        assert_eq!(img.as_slice(), &[0, 128, 255, 255]);
+    }
+
+    #[test]
+    fn to_image_invert_flips_around_white() {
+        let page = RawPage::new(1, 2, 1, RawSamples::U8(vec![0, 255]));
+        let img = page.to_image(127.5, 255.0, true);
+        assert_eq!(img.as_slice(), &[255, 0]);
+    }
+}
@@ -1,118 +1,244 @@
 //! TIFF image reading and conversion utilities.
 //!
-//! This module provides functionality to read TIFF files and convert various
-//! pixel formats to 8-bit grayscale for display.
+//! This module provides functionality to read TIFF files into [`RawPage`]s,
+//! routing grayscale, RGB, RGBA, and palette-color pages to the appropriate
+//! channel layout, and converting CMYK and YCbCr pages to RGB so every page
+//! ends up in a channel layout the rest of the crate (and the UI) already
+//! understands. Samples are otherwise kept in their native bit depth; see
+//! [`crate::rawimage`] for how they're mapped to 8-bit at display time.
 
 use num_traits::cast::ToPrimitive;
-use num_traits::float::Float;
 use std::fs::File;
 use tiff::decoder::{Decoder, DecodingResult};
+use tiff::tags::Tag;
+use tiff::ColorType;
 
-use crate::image::Image;
+use crate::rawimage::{RawPage, RawSamples};
 
-/// Converts floating-point pixel data to 8-bit grayscale.
-///
-/// Normalizes the values to the [0, 1] range based on min/max values in the buffer,
-/// then scales to [0, 255] for u8 representation.
-///
-/// # Arguments
-///
-/// * `buf` - Slice of floating-point pixel values
-///
-/// # Returns
-///
-/// Vector of 8-bit pixel values
-fn to_u8_float<T>(buf: &[T]) -> Vec<u8>
-where
-    T: Float + ToPrimitive + Copy,
-{
-    if buf.is_empty() {
-        return Vec::new();
+/// Converts a decoded TIFF page into [`RawSamples`], preserving its native
+/// numeric type (no normalization).
+fn decoding_result_to_raw(result: DecodingResult) -> RawSamples {
+    match result {
+        DecodingResult::U8(buf) => RawSamples::U8(buf),
+        DecodingResult::U16(buf) => RawSamples::U16(buf),
+        DecodingResult::U32(buf) => RawSamples::U32(buf),
+        DecodingResult::U64(buf) => RawSamples::U64(buf),
+        DecodingResult::F32(buf) => RawSamples::F32(buf),
+        DecodingResult::F64(buf) => RawSamples::F64(buf),
+        DecodingResult::I8(buf) => RawSamples::I8(buf),
+        DecodingResult::I16(buf) => RawSamples::I16(buf),
+        DecodingResult::I32(buf) => RawSamples::I32(buf),
+        DecodingResult::I64(buf) => RawSamples::I64(buf),
     }
+}
 
-    let minv = buf.iter().copied().reduce(T::min).unwrap();
-    let maxv = buf.iter().copied().reduce(T::max).unwrap();
-    let span = if maxv != minv { maxv - minv } else { T::one() };
-    let max_scaled = u8::MAX as f64;
-
-    buf.iter()
-        .copied()
-        .map(|value| {
-            let normalized = (value - minv) / span;
-            let frac = normalized.to_f64().unwrap_or(0.0).clamp(0.0, 1.0);
-            (frac * max_scaled) as u8
-        })
-        .collect()
+/// Converts a decoded TIFF page to raw unsigned indices, without any
+/// normalization. Used for palette images, where the decoded samples are
+/// ColorMap indices rather than intensities.
+fn page_to_indices(result: DecodingResult) -> Vec<usize> {
+    fn widen<T: ToPrimitive>(buf: Vec<T>) -> Vec<usize> {
+        buf.iter().map(|v| v.to_usize().unwrap_or(0)).collect()
+    }
+
+    match result {
+        DecodingResult::U8(buf) => widen(buf),
+        DecodingResult::U16(buf) => widen(buf),
+        DecodingResult::U32(buf) => widen(buf),
+        DecodingResult::U64(buf) => widen(buf),
+        DecodingResult::F32(buf) => widen(buf),
+        DecodingResult::F64(buf) => widen(buf),
+        DecodingResult::I8(buf) => widen(buf),
+        DecodingResult::I16(buf) => widen(buf),
+        DecodingResult::I32(buf) => widen(buf),
+        DecodingResult::I64(buf) => widen(buf),
+    }
 }
 
-/// Converts integer pixel data to 8-bit grayscale.
-///
-/// Normalizes the values to the [0, 1] range based on min/max values in the buffer,
-/// then scales to [0, 255] for u8 representation.
-///
-/// # Arguments
-///
-/// * `buf` - Slice of integer pixel values
-///
-/// # Returns
-///
-/// Vector of 8-bit pixel values
-fn to_u8_int<T>(buf: &[T]) -> Vec<u8>
-where
-    T: ToPrimitive + Copy + PartialOrd,
-{
-    if buf.is_empty() {
-        return Vec::new();
+/// Converts a decoded TIFF page to `f64` samples, without normalization (the
+/// CMYK/YCbCr to RGB conversions below divide by [`decoding_result_max`]
+/// themselves). Used by those conversions, which need to do arithmetic
+/// across channels regardless of the page's native bit depth.
+fn decoding_result_to_f64(result: DecodingResult) -> Vec<f64> {
+    fn widen<T: ToPrimitive>(buf: Vec<T>) -> Vec<f64> {
+        buf.iter().map(|v| v.to_f64().unwrap_or(0.0)).collect()
     }
 
-    let minv = buf.iter().copied().min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap();
-    let maxv = buf.iter().copied().max_by(|a, b| a.partial_cmp(b).unwrap()).unwrap();
-    
-    let min_f64 = minv.to_f64().unwrap_or(0.0);
-    let max_f64 = maxv.to_f64().unwrap_or(0.0);
-    let span = if max_f64 != min_f64 { max_f64 - min_f64 } else { 1.0 };
-
-    buf.iter()
-        .copied()
-        .map(|value| {
-            let val_f64 = value.to_f64().unwrap_or(0.0);
-            let normalized = (val_f64 - min_f64) / span;
-            (normalized * 255.0).clamp(0.0, 255.0) as u8
+    match result {
+        DecodingResult::U8(buf) => widen(buf),
+        DecodingResult::U16(buf) => widen(buf),
+        DecodingResult::U32(buf) => widen(buf),
+        DecodingResult::U64(buf) => widen(buf),
+        DecodingResult::F32(buf) => widen(buf),
+        DecodingResult::F64(buf) => widen(buf),
+        DecodingResult::I8(buf) => widen(buf),
+        DecodingResult::I16(buf) => widen(buf),
+        DecodingResult::I32(buf) => widen(buf),
+        DecodingResult::I64(buf) => widen(buf),
+    }
+}
+
+/// Returns the maximum value representable by a decoded page's numeric type,
+/// used to normalize its samples to [0, 1] before converting between color
+/// spaces. Floating-point TIFF samples are assumed to already be normalized.
+fn decoding_result_max(result: &DecodingResult) -> f64 {
+    match result {
+        DecodingResult::U8(_) => u8::MAX as f64,
+        DecodingResult::U16(_) => u16::MAX as f64,
+        DecodingResult::U32(_) => u32::MAX as f64,
+        DecodingResult::U64(_) => u64::MAX as f64,
+        DecodingResult::F32(_) => 1.0,
+        DecodingResult::F64(_) => 1.0,
+        DecodingResult::I8(_) => i8::MAX as f64,
+        DecodingResult::I16(_) => i16::MAX as f64,
+        DecodingResult::I32(_) => i32::MAX as f64,
+        DecodingResult::I64(_) => i64::MAX as f64,
+    }
+}
+
+/// Converts interleaved CMYK samples (normalized by `max`) into interleaved
+/// 8-bit RGB samples using the standard naive CMYK -> RGB formula.
+fn cmyk_to_rgb(samples: &[f64], max: f64) -> Vec<u8> {
+    samples
+        .chunks_exact(4)
+        .flat_map(|px| {
+            let (c, m, y, k) = (px[0] / max, px[1] / max, px[2] / max, px[3] / max);
+            [
+                ((1.0 - c) * (1.0 - k) * 255.0).round() as u8,
+                ((1.0 - m) * (1.0 - k) * 255.0).round() as u8,
+                ((1.0 - y) * (1.0 - k) * 255.0).round() as u8,
+            ]
         })
         .collect()
 }
 
-/// Converts a decoded TIFF page to 8-bit pixel data.
+/// Converts interleaved YCbCr samples (normalized by `max`) into interleaved
+/// 8-bit RGB samples using the ITU-R BT.601 conversion.
+fn ycbcr_to_rgb(samples: &[f64], max: f64) -> Vec<u8> {
+    let center = max / 2.0;
+    samples
+        .chunks_exact(3)
+        .flat_map(|px| {
+            let (y, cb, cr) = (px[0], px[1] - center, px[2] - center);
+            let to_u8 = |v: f64| (v / max * 255.0).clamp(0.0, 255.0).round() as u8;
+            [
+                to_u8(y + 1.402 * cr),
+                to_u8(y - 0.344136 * cb - 0.714136 * cr),
+                to_u8(y + 1.772 * cb),
+            ]
+        })
+        .collect()
+}
+
+/// Expands a buffer of palette indices into interleaved 8-bit RGB samples
+/// using a TIFF `ColorMap` tag.
 ///
-/// Handles various pixel formats (U8, U16, U32, U64, F32, F64, I8, I16, I32, I64)
-/// and converts them to normalized 8-bit values.
+/// The `ColorMap` tag stores three consecutive planes (red, green, blue) of
+/// 16-bit entries, one entry per possible index value. Each 16-bit entry is
+/// scaled down to 8-bit by taking its high byte.
 ///
 /// # Arguments
 ///
-/// * `result` - The decoded TIFF page data
+/// * `indices` - Palette indices, one per pixel
+/// * `colormap` - The raw `ColorMap` tag values (three concatenated 16-bit planes)
 ///
 /// # Returns
 ///
-/// Vector of 8-bit pixel values
-fn page_to_u8(result: DecodingResult) -> Vec<u8> {
+/// Vector of interleaved 8-bit RGB samples, three per pixel
+fn expand_palette(indices: &[usize], colormap: &[u16]) -> Vec<u8> {
+    let entries = colormap.len() / 3;
+    let mut rgb = Vec::with_capacity(indices.len() * 3);
+    for &idx in indices {
+        if entries == 0 {
+            // Malformed ColorMap (fewer than 3 entries): there's nothing to
+            // index into, so fall back to neutral gray instead of panicking.
+            rgb.extend_from_slice(&[128, 128, 128]);
+            continue;
+        }
+        let idx = idx.min(entries - 1);
+        let r = (colormap[idx] >> 8) as u8;
+        let g = (colormap[entries + idx] >> 8) as u8;
+        let b = (colormap[2 * entries + idx] >> 8) as u8;
+        rgb.push(r);
+        rgb.push(g);
+        rgb.push(b);
+    }
+    rgb
+}
+
+/// Returns how many samples a decoded page holds, regardless of its numeric
+/// type. Used to recover the true samples-per-pixel (`len / pixel_count`)
+/// for `ColorType`s not handled explicitly in [`decode_page`].
+fn decoding_result_len(result: &DecodingResult) -> usize {
     match result {
-        DecodingResult::U8(buf) => buf,
-        DecodingResult::U16(buf) => to_u8_int(&buf),
-        DecodingResult::U32(buf) => to_u8_int(&buf),
-        DecodingResult::U64(buf) => to_u8_int(&buf),
-        DecodingResult::F32(buf) => to_u8_float(&buf),
-        DecodingResult::F64(buf) => to_u8_float(&buf),
-        DecodingResult::I8(buf) => to_u8_int(&buf),
-        DecodingResult::I16(buf) => to_u8_int(&buf),
-        DecodingResult::I32(buf) => to_u8_int(&buf),
-        DecodingResult::I64(buf) => to_u8_int(&buf),
+        DecodingResult::U8(buf) => buf.len(),
+        DecodingResult::U16(buf) => buf.len(),
+        DecodingResult::U32(buf) => buf.len(),
+        DecodingResult::U64(buf) => buf.len(),
+        DecodingResult::F32(buf) => buf.len(),
+        DecodingResult::F64(buf) => buf.len(),
+        DecodingResult::I8(buf) => buf.len(),
+        DecodingResult::I16(buf) => buf.len(),
+        DecodingResult::I32(buf) => buf.len(),
+        DecodingResult::I64(buf) => buf.len(),
     }
 }
 
-/// Reads a TIFF file and extracts all pages as Image objects.
+/// Reads one decoded page into a [`RawPage`], routing grayscale, RGB, RGBA,
+/// and palette-color data according to the page's `PhotometricInterpretation`
+/// / `SamplesPerPixel` (surfaced by the decoder as a [`ColorType`]), and
+/// converting CMYK and YCbCr data to RGB.
+fn decode_page(decoder: &mut Decoder<File>) -> Result<RawPage, Box<dyn std::error::Error>> {
+    let (width, height) = decoder.dimensions()?;
+    let color_type = decoder.colortype()?;
+    let page = decoder.read_image()?;
+
+    let (channels, samples) = match color_type {
+        ColorType::Gray(_) => (1, decoding_result_to_raw(page)),
+        ColorType::GrayA(_) => (2, decoding_result_to_raw(page)),
+        ColorType::RGB(_) => (3, decoding_result_to_raw(page)),
+        ColorType::RGBA(_) => (4, decoding_result_to_raw(page)),
+        ColorType::CMYK(_) => {
+            let max = decoding_result_max(&page);
+            let samples = decoding_result_to_f64(page);
+            (3, RawSamples::U8(cmyk_to_rgb(&samples, max)))
+        }
+        ColorType::YCbCr(_) => {
+            let max = decoding_result_max(&page);
+            let samples = decoding_result_to_f64(page);
+            (3, RawSamples::U8(ycbcr_to_rgb(&samples, max)))
+        }
+        ColorType::Palette(_) => {
+            let colormap = decoder.get_tag(Tag::ColorMap)?.into_u16_vec()?;
+            let indices = page_to_indices(page);
+            (3, RawSamples::U8(expand_palette(&indices, &colormap)))
+        }
+        // Anything else: recover the true samples-per-pixel from the decoded
+        // buffer itself (it always holds the real SamplesPerPixel, regardless
+        // of whether we recognize this PhotometricInterpretation) rather than
+        // assuming 1, which would panic `RawPage::new`'s size assertion.
+        _ => {
+            let pixel_count = (width as usize * height as usize).max(1);
+            let channels = (decoding_result_len(&page) / pixel_count).max(1);
+            (channels, decoding_result_to_raw(page))
+        }
+    };
+
+    Ok(RawPage::new(
+        height as usize,
+        width as usize,
+        channels,
+        samples,
+    ))
+}
+
+/// Reads a TIFF file and extracts all pages as [`RawPage`]s.
 ///
-/// Supports multi-page TIFF files and various pixel formats. Each page is
-/// converted to 8-bit grayscale regardless of the original bit depth or format.
+/// Supports multi-page TIFF files and various pixel formats. Grayscale, RGB,
+/// RGBA, and palette-color pages are each routed to the appropriate channel
+/// layout; CMYK and YCbCr pages are converted to RGB; anything else has its
+/// channel count recovered from the decoded buffer size. Samples are kept at
+/// their original bit depth (CMYK/YCbCr excepted, which become 8-bit RGB);
+/// use [`RawPage::to_image`] to render them to 8-bit for display.
 ///
 /// # Arguments
 ///
@@ -120,7 +246,7 @@ fn page_to_u8(result: DecodingResult) -> Vec<u8> {
 ///
 /// # Returns
 ///
-/// * `Ok(Vec<Image>)` - Vector of images, one for each page in the TIFF file
+/// * `Ok(Vec<RawPage>)` - One entry per page in the TIFF file
 /// * `Err` - If the file cannot be opened, decoded, or is not a valid TIFF
 ///
 /// # Examples
@@ -128,21 +254,16 @@ fn page_to_u8(result: DecodingResult) -> Vec<u8> {
 /// ```no_run
 /// use tiffview::tifread::read_tiff;
 ///
-/// let images = read_tiff("image.tif").expect("Failed to read TIFF");
-/// println!("Loaded {} pages", images.len());
+/// let pages = read_tiff("image.tif").expect("Failed to read TIFF");
+/// println!("Loaded {} pages", pages.len());
 /// ```
-pub fn read_tiff(path: &str) -> Result<Vec<Image>, Box<dyn std::error::Error>> {
+pub fn read_tiff(path: &str) -> Result<Vec<RawPage>, Box<dyn std::error::Error>> {
     let fp = File::open(path)?;
     let mut decoder = Decoder::new(fp)?;
-    let mut stack = Vec::<Image>::new();
+    let mut stack = Vec::<RawPage>::new();
 
     loop {
-        let (_width, _height) = decoder.dimensions()?;
-
-        let page = decoder.read_image()?;
-        let decoded = page_to_u8(page);
-        let img = Image::from_vec(_height as usize, _width as usize, decoded);
-        stack.push(img);
+        stack.push(decode_page(&mut decoder)?);
 
         if decoder.next_image().is_err() {
             break;
@@ -151,3 +272,29 @@ pub fn read_tiff(path: &str) -> Result<Vec<Image>, Box<dyn std::error::Error>> {
 
     Ok(stack)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_palette_looks_up_known_entries() {
+        // Two entries: index 0 -> pure red, index 1 -> pure green.
+        let colormap = vec![0xFFFF, 0x0000, 0x0000, 0x0000, 0xFFFF, 0x0000];
+        let rgb = expand_palette(&[1, 0], &colormap);
+        assert_eq!(rgb, vec![0, 255, 0, 255, 0, 0]);
+    }
+
+    #[test]
+    fn expand_palette_clamps_out_of_range_index_to_last_entry() {
+        let colormap = vec![0xFFFF, 0x0000, 0x0000, 0x0000, 0xFFFF, 0x0000];
+        let rgb = expand_palette(&[99], &colormap);
+        assert_eq!(rgb, vec![0, 255, 0]);
+    }
+
+    #[test]
+    fn expand_palette_falls_back_to_gray_on_malformed_colormap() {
+        let rgb = expand_palette(&[0, 1], &[0x1234]);
+        assert_eq!(rgb, vec![128, 128, 128, 128, 128, 128]);
+    }
+}
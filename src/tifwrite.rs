@@ -0,0 +1,123 @@
+//! TIFF image writing utilities.
+//!
+//! Companion to [`crate::tifread`]: encodes one or more in-memory [`Image`]s
+//! back out to a TIFF file using the `tiff` crate's encoder, with a
+//! selectable compression scheme. A single `Image` becomes a single-page
+//! TIFF; a slice of images becomes a multi-page TIFF.
+
+use std::fs::File;
+use std::io::{BufWriter, Seek, Write};
+
+use tiff::encoder::{colortype, compression, TiffEncoder};
+
+use crate::image::Image;
+
+/// Compression scheme to use when writing a TIFF file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// No compression; largest files, fastest to write.
+    #[default]
+    Uncompressed,
+    /// Lempel-Ziv-Welch compression.
+    Lzw,
+    /// Zlib/Deflate compression.
+    Deflate,
+    /// PackBits run-length compression.
+    PackBits,
+}
+
+/// Encodes a single page's samples into `encoder` using the given compression.
+///
+/// `C` pins the pixel layout (grayscale, RGB, or RGBA); `img`'s channel count
+/// must match it, which [`write_page_any`] is responsible for guaranteeing.
+fn write_page<W, C>(
+    encoder: &mut TiffEncoder<W>,
+    img: &Image,
+    compression: Compression,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    W: Write + Seek,
+    C: colortype::ColorType<Inner = u8>,
+{
+    let width = img.cols() as u32;
+    let height = img.rows() as u32;
+    let data = img.as_slice();
+
+    match compression {
+        Compression::Uncompressed => {
+            encoder.write_image::<C>(width, height, data)?;
+        }
+        Compression::Lzw => {
+            encoder
+                .new_image_with_compression::<C, _>(width, height, compression::Lzw)?
+                .write_data(data)?;
+        }
+        Compression::Deflate => {
+            encoder
+                .new_image_with_compression::<C, _>(width, height, compression::Deflate::default())?
+                .write_data(data)?;
+        }
+        Compression::PackBits => {
+            encoder
+                .new_image_with_compression::<C, _>(width, height, compression::Packbits)?
+                .write_data(data)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Dispatches to the [`colortype`] matching `img`'s channel count.
+fn write_page_any<W>(
+    encoder: &mut TiffEncoder<W>,
+    img: &Image,
+    compression: Compression,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    W: Write + Seek,
+{
+    match img.channels() {
+        1 => write_page::<W, colortype::Gray8>(encoder, img, compression),
+        3 => write_page::<W, colortype::RGB8>(encoder, img, compression),
+        4 => write_page::<W, colortype::RGBA8>(encoder, img, compression),
+        n => Err(format!("unsupported channel count for TIFF export: {n}").into()),
+    }
+}
+
+/// Writes `images` to `path` as a multi-page TIFF using the given compression.
+///
+/// # Arguments
+///
+/// * `path` - Destination file path
+/// * `images` - Pages to write, in order
+/// * `compression` - Compression scheme applied to every page
+pub fn write_tiff(
+    path: &str,
+    images: &[Image],
+    compression: Compression,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let file = File::create(path)?;
+    let mut writer = BufWriter::new(file);
+    let mut encoder = TiffEncoder::new(&mut writer)?;
+
+    for img in images {
+        write_page_any(&mut encoder, img, compression)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a single `image` to `path` as a single-page TIFF.
+///
+/// # Arguments
+///
+/// * `path` - Destination file path
+/// * `image` - The page to write
+/// * `compression` - Compression scheme to apply
+pub fn write_page_to_file(
+    path: &str,
+    image: &Image,
+    compression: Compression,
+) -> Result<(), Box<dyn std::error::Error>> {
+    write_tiff(path, std::slice::from_ref(image), compression)
+}
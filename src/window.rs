@@ -1,36 +1,106 @@
-use crate::image::Image;
+use crate::image::{Image, ResampleMode};
+use crate::rawimage::RawPage;
+use crate::tifwrite::{self, Compression};
 use egui::{ColorImage, TextureHandle, TextureOptions};
 
 pub struct ImageViewer {
-    images: Vec<Image>,
+    pages: Vec<RawPage>,
     current_index: usize,
     texture: Option<TextureHandle>,
+    rendered_image: Option<Image>,
     scaled_image: Option<Image>,
     last_scale_factor: f32,
+    resample_mode: ResampleMode,
+    window_center: f64,
+    window_width: f64,
+    invert: bool,
+    save_compression: Compression,
+    save_status: Option<String>,
 }
 
 impl ImageViewer {
-    pub fn new(images: Vec<Image>) -> Self {
+    pub fn new(pages: Vec<RawPage>) -> Self {
+        let (window_center, window_width) = pages
+            .first()
+            .map(RawPage::default_window)
+            .unwrap_or((127.5, 255.0));
+
         Self {
-            images,
+            pages,
             current_index: 0,
             texture: None,
+            rendered_image: None,
             scaled_image: None,
             last_scale_factor: 1.0,
+            resample_mode: ResampleMode::default(),
+            window_center,
+            window_width,
+            invert: false,
+            save_compression: Compression::default(),
+            save_status: None,
         }
     }
 
+    /// Invalidates everything derived from the current page's window/level
+    /// settings, forcing a re-render on the next frame.
+    fn invalidate_render(&mut self) {
+        self.texture = None;
+        self.rendered_image = None;
+        self.scaled_image = None;
+    }
+
+    fn current_page(&self) -> &RawPage {
+        &self.pages[self.current_index]
+    }
+
+    /// Saves the currently displayed page (at its current window/level) to
+    /// `tiffview_export.tif`.
+    fn save_current_page(&mut self) {
+        let path = "tiffview_export.tif";
+        let image = self
+            .current_page()
+            .to_image(self.window_center, self.window_width, self.invert);
+        let result = tifwrite::write_page_to_file(path, &image, self.save_compression);
+        self.save_status = Some(match result {
+            Ok(()) => format!("Saved page {} to {path}", self.current_index + 1),
+            Err(e) => format!("Failed to save {path}: {e}"),
+        });
+    }
+
+    /// Saves every page (each at the current window/level) to
+    /// `tiffview_export_stack.tif` as a multi-page TIFF.
+    fn save_stack(&mut self) {
+        let path = "tiffview_export_stack.tif";
+        let images: Vec<Image> = self
+            .pages
+            .iter()
+            .map(|p| p.to_image(self.window_center, self.window_width, self.invert))
+            .collect();
+        let result = tifwrite::write_tiff(path, &images, self.save_compression);
+        self.save_status = Some(match result {
+            Ok(()) => format!("Saved {} page(s) to {path}", images.len()),
+            Err(e) => format!("Failed to save {path}: {e}"),
+        });
+    }
+
     fn update_texture(&mut self, ctx: &egui::Context, scale_factor: f32) {
-        if self.images.is_empty() {
+        if self.pages.is_empty() {
             return;
         }
 
+        if self.rendered_image.is_none() {
+            let page = self.current_page();
+            self.rendered_image =
+                Some(page.to_image(self.window_center, self.window_width, self.invert));
+            self.scaled_image = None;
+        }
+
         // Only rescale if the scale factor changed significantly
         let needs_rescale = (scale_factor - self.last_scale_factor).abs() > 0.01;
 
         if needs_rescale || self.scaled_image.is_none() {
-            let img = &self.images[self.current_index];
-            self.scaled_image = Some(img.scale(scale_factor));
+            let img = self.rendered_image.as_ref().unwrap();
+            self.scaled_image = Some(img.scale_with_mode(scale_factor, self.resample_mode));
             self.last_scale_factor = scale_factor;
         }
 
@@ -38,11 +108,16 @@ impl ImageViewer {
             let width = scaled_img.cols();
             let height = scaled_img.rows();
 
-            // Convert grayscale u8 to RGBA
+            // Convert the image's interleaved samples to RGBA, based on its channel count
+            let channels = scaled_img.channels();
             let pixels: Vec<egui::Color32> = scaled_img
                 .as_slice()
-                .iter()
-                .map(|&gray| egui::Color32::from_gray(gray))
+                .chunks(channels)
+                .map(|px| match channels {
+                    3 => egui::Color32::from_rgb(px[0], px[1], px[2]),
+                    4 => egui::Color32::from_rgba_unmultiplied(px[0], px[1], px[2], px[3]),
+                    _ => egui::Color32::from_gray(px[0]),
+                })
                 .collect();
 
             let color_image = ColorImage {
@@ -55,17 +130,20 @@ impl ImageViewer {
     }
 
     fn navigate(&mut self, delta: isize) {
-        if self.images.is_empty() {
+        if self.pages.is_empty() {
             return;
         }
 
         let new_index =
-            (self.current_index as isize + delta).rem_euclid(self.images.len() as isize) as usize;
+            (self.current_index as isize + delta).rem_euclid(self.pages.len() as isize) as usize;
 
         if new_index != self.current_index {
             self.current_index = new_index;
+            let (center, width) = self.current_page().default_window();
+            self.window_center = center;
+            self.window_width = width;
             self.texture = None;
-            self.scaled_image = None;
+            self.invalidate_render();
         }
     }
 }
@@ -79,18 +157,96 @@ impl eframe::App for ImageViewer {
         if ctx.input(|i| i.key_pressed(egui::Key::ArrowLeft)) {
             self.navigate(-1);
         }
+        if !self.pages.is_empty() {
+            let save_stack = ctx.input(|i| i.modifiers.command && i.modifiers.shift && i.key_pressed(egui::Key::S));
+            let save_page = ctx.input(|i| i.modifiers.command && !i.modifiers.shift && i.key_pressed(egui::Key::S));
+            if save_stack {
+                self.save_stack();
+            } else if save_page {
+                self.save_current_page();
+            }
+        }
 
         egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading(format!(
-                "Image {} of {}",
-                self.current_index + 1,
-                self.images.len()
-            ));
+            ui.horizontal(|ui| {
+                ui.heading(format!(
+                    "Image {} of {}",
+                    self.current_index + 1,
+                    self.pages.len()
+                ));
+
+                ui.separator();
+                ui.label("Filter:");
+                let previous_mode = self.resample_mode;
+                egui::ComboBox::from_id_salt("resample_mode")
+                    .selected_text(format!("{:?}", self.resample_mode))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.resample_mode, ResampleMode::Nearest, "Nearest");
+                        ui.selectable_value(&mut self.resample_mode, ResampleMode::Bilinear, "Bilinear");
+                        ui.selectable_value(&mut self.resample_mode, ResampleMode::Bicubic, "Bicubic");
+                    });
+                if self.resample_mode != previous_mode {
+                    self.scaled_image = None;
+                }
+
+                ui.separator();
+                ui.label("Compression:");
+                egui::ComboBox::from_id_salt("save_compression")
+                    .selected_text(format!("{:?}", self.save_compression))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.save_compression, Compression::Uncompressed, "Uncompressed");
+                        ui.selectable_value(&mut self.save_compression, Compression::Lzw, "LZW");
+                        ui.selectable_value(&mut self.save_compression, Compression::Deflate, "Deflate");
+                        ui.selectable_value(&mut self.save_compression, Compression::PackBits, "PackBits");
+                    });
+                if !self.pages.is_empty() {
+                    if ui.button("Save page (Ctrl+S)").clicked() {
+                        self.save_current_page();
+                    }
+                    if ui.button("Save stack (Ctrl+Shift+S)").clicked() {
+                        self.save_stack();
+                    }
+                }
+            });
+
+            if !self.pages.is_empty() {
+                let (page_min, page_max) = (self.current_page().min(), self.current_page().max());
+                let mut changed = false;
+
+                ui.horizontal(|ui| {
+                    ui.label("Window center:");
+                    changed |= ui
+                        .add(egui::Slider::new(&mut self.window_center, page_min..=page_max))
+                        .changed();
+
+                    ui.label("Window width:");
+                    changed |= ui
+                        .add(egui::Slider::new(&mut self.window_width, 1e-6..=(page_max - page_min).max(1e-6)))
+                        .changed();
+
+                    changed |= ui.checkbox(&mut self.invert, "Invert").changed();
+
+                    if ui.button("Auto-contrast").clicked() {
+                        let (center, width) = self.current_page().auto_window();
+                        self.window_center = center;
+                        self.window_width = width;
+                        changed = true;
+                    }
+                });
+
+                if changed {
+                    self.invalidate_render();
+                }
+            }
+
+            if let Some(status) = &self.save_status {
+                ui.label(status);
+            }
 
-            if !self.images.is_empty() {
+            if !self.pages.is_empty() {
                 let available_size = ui.available_size();
-                let img = &self.images[self.current_index];
-                let img_size = egui::vec2(img.cols() as f32, img.rows() as f32);
+                let page = self.current_page();
+                let img_size = egui::vec2(page.cols() as f32, page.rows() as f32);
 
                 // Calculate scale to fit window while maintaining aspect ratio
                 let scale = (available_size.x / img_size.x).min(available_size.y / img_size.y);
@@ -123,10 +279,10 @@ impl eframe::App for ImageViewer {
     }
 }
 
-pub fn run(images: Vec<Image>) -> Result<(), eframe::Error> {
-    // Get the size of the first image to set initial window size
-    let initial_size = if let Some(first_img) = images.first() {
-        [first_img.cols() as f32, first_img.rows() as f32 + 30.0] // +30 for header
+pub fn run(pages: Vec<RawPage>) -> Result<(), eframe::Error> {
+    // Get the size of the first page to set initial window size
+    let initial_size = if let Some(first_page) = pages.first() {
+        [first_page.cols() as f32, first_page.rows() as f32 + 30.0] // +30 for header
     } else {
         [800.0, 600.0]
     };
@@ -142,6 +298,6 @@ pub fn run(images: Vec<Image>) -> Result<(), eframe::Error> {
     eframe::run_native(
         "tiffview",
         options,
-        Box::new(|_cc| Ok(Box::new(ImageViewer::new(images)))),
+        Box::new(|_cc| Ok(Box::new(ImageViewer::new(pages)))),
     )
 }